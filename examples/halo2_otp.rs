@@ -10,17 +10,16 @@ const RATE: usize = 2;
 const R_F: usize = 8;
 const R_P: usize = 57;
 
-// Valid for 100 years; TOTP interval 30 seconds
-// 100 * 365 * 24 * 60 * 2 = 105120000
-// log2 (105120000) = 27
-const LEVELS: usize = 27;
-
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
     pub otp: String, // field element, but easier to deserialize as a string
     pub time: String,
-    pub path_elements: [String; LEVELS],
-    pub path_index: [String; LEVELS]
+    pub anchor: String, // caller-supplied tree root the proof must be checked against
+    // Must equal path_elements.len() == path_index.len(). A depth of 27 supports 100 years of
+    // 30-second TOTP intervals (100 * 365 * 24 * 60 * 2 = 105120000, log2 of which is ~27).
+    pub depth: usize,
+    pub path_elements: Vec<String>,
+    pub path_index: Vec<String>,
 }
 
 fn otp_merkle_proof<F: ScalarField>(
@@ -31,13 +30,20 @@ fn otp_merkle_proof<F: ScalarField>(
     // `Context` can roughly be thought of as a single-threaded execution trace of a program we want to ZK prove. We do some post-processing on `Context` to optimally divide the execution trace into multiple columns in a PLONKish arithmetization
     // More advanced usage with multi-threaded witness generation is possible, but we do not explain it here
 
+    assert_eq!(input.path_elements.len(), input.depth, "path_elements length must equal configured depth");
+    assert_eq!(input.path_index.len(), input.depth, "path_index length must equal configured depth");
+
     let otp = F::from_str_vartime(&input.otp).expect("deserialize field element should not fail");
     let time = F::from_str_vartime(&input.time).expect("deserialize field element should not fail");
-    let path_elements = input.path_elements.map(|x: String| ctx.load_witness(F::from_str_vartime(&x).unwrap()));
-    let path_index = input.path_index.map(|x: String| ctx.load_witness(F::from_str_vartime(&x).unwrap()));
+    let anchor = F::from_str_vartime(&input.anchor).expect("deserialize field element should not fail");
+    let path_elements: Vec<_> =
+        input.path_elements.iter().map(|x| ctx.load_witness(F::from_str_vartime(x).unwrap())).collect();
+    let path_index: Vec<_> =
+        input.path_index.iter().map(|x| ctx.load_witness(F::from_str_vartime(x).unwrap())).collect();
 
     let otp = ctx.load_witness(otp);
     let time = ctx.load_witness(time);
+    let anchor = ctx.load_witness(anchor);
     make_public.push(time);
 
     // create a Gate chip that contains methods for basic arithmetic operations
@@ -50,7 +56,7 @@ fn otp_merkle_proof<F: ScalarField>(
     let mut level_hashes = vec![];
     level_hashes.push(leaf.clone());
 
-    for i in 0..LEVELS {
+    for i in 0..input.depth {
         // Should be 0 or 1
         gate.assert_bit(ctx, path_index[i].clone());
 
@@ -65,9 +71,13 @@ fn otp_merkle_proof<F: ScalarField>(
         level_hashes.push(inner_poseidon.squeeze(ctx, &gate).unwrap());
     }
 
-    let root = level_hashes[LEVELS];
-    make_public.push(root);
-    println!("otp: {:?}, time: {:?}, root: {:?}", otp.value(), time.value(), root.value());
+    let root = level_hashes[input.depth];
+    // Following the Orchard anchor pattern: the computed root is only meaningful once it's tied
+    // to a known, caller-supplied anchor, so constrain the two equal and expose the anchor (not
+    // the root) as the public instance.
+    gate.assert_equal(ctx, root, anchor);
+    make_public.push(anchor);
+    println!("otp: {:?}, time: {:?}, anchor: {:?}", otp.value(), time.value(), anchor.value());
 }
 
 fn main() {
@@ -87,12 +97,38 @@ mod tests {
 
     #[test]
     fn test_otp_merkle_proof() {
-        let params = CircuitInput {
-            otp: "12345".to_string(),
-            time: "3155000".to_string(),
-            path_elements: ["1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999"].map(|x| x.to_string()),
-            path_index: ["1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1"].map(|x| x.to_string())
-        };
+        let otp = "12345".to_string();
+        let time = "3155000".to_string();
+        let path_elements: Vec<String> = ["1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999"].map(|x| x.to_string()).to_vec();
+        let path_index: Vec<String> = ["1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1"].map(|x| x.to_string()).to_vec();
+        let depth = path_elements.len();
+
+        // Compute the expected root natively (same Poseidon chain the circuit builds) so we can
+        // supply it as the `anchor` the circuit is required to match.
+        let mut probe_builder = GateThreadBuilder::<Fr>::mock();
+        let probe_ctx = probe_builder.main(0);
+        let gate = GateChip::<Fr>::default();
+        let otp_w = probe_ctx.load_witness(Fr::from_str_vartime(&otp).unwrap());
+        let time_w = probe_ctx.load_witness(Fr::from_str_vartime(&time).unwrap());
+        let path_elements_w: Vec<_> =
+            path_elements.iter().map(|x| probe_ctx.load_witness(Fr::from_str_vartime(x).unwrap())).collect();
+        let path_index_w: Vec<_> =
+            path_index.iter().map(|x| probe_ctx.load_witness(Fr::from_str_vartime(x).unwrap())).collect();
+        let mut poseidon = PoseidonChip::<Fr, T, RATE>::new(probe_ctx, R_F, R_P).unwrap();
+        poseidon.update(&[time_w, otp_w]);
+        let mut root = poseidon.squeeze(probe_ctx, &gate).unwrap();
+        for i in 0..depth {
+            let mut inner_poseidon = PoseidonChip::<Fr, T, RATE>::new(probe_ctx, R_F, R_P).unwrap();
+            if *path_index_w[i].value() == Fr::zero() {
+                inner_poseidon.update(&[root, path_elements_w[i]]);
+            } else {
+                inner_poseidon.update(&[path_elements_w[i], root]);
+            }
+            root = inner_poseidon.squeeze(probe_ctx, &gate).unwrap();
+        }
+        let anchor = halo2_base::utils::fe_to_biguint(root.value()).to_string();
+
+        let params = CircuitInput { otp, time, anchor, depth, path_elements, path_index };
 
         let k = 10u32;
 
@@ -109,4 +145,36 @@ mod tests {
 
         println!("Public inputs: {:?}", make_public);
     }
+
+    /// `test_otp_merkle_proof` above only exercises `anchor` already matching the computed root,
+    /// so it would never notice if `gate.assert_equal(ctx, root, anchor)` were accidentally
+    /// dropped. Tamper with `anchor` so it no longer matches the Merkle chain's real root, and
+    /// confirm the circuit actually rejects it.
+    #[test]
+    fn test_otp_merkle_proof_rejects_wrong_anchor() {
+        let otp = "12345".to_string();
+        let time = "3155000".to_string();
+        let path_elements: Vec<String> = ["1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999"].map(|x| x.to_string()).to_vec();
+        let path_index: Vec<String> = ["1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1"].map(|x| x.to_string()).to_vec();
+        let depth = path_elements.len();
+
+        // Deliberately wrong: a plausible-looking field element that is not the real Merkle root.
+        let anchor = "1".to_string();
+
+        let params = CircuitInput { otp, time, anchor, depth, path_elements, path_index };
+
+        let k = 10u32;
+        let mut make_public = vec![];
+
+        let mut builder = GateThreadBuilder::<Fr>::mock();
+        otp_merkle_proof(builder.main(0), params, &mut make_public);
+
+        builder.config(k as usize, Some(12));
+
+        let circuit = GateCircuitBuilder::mock(builder);
+        assert!(
+            MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err(),
+            "assert_equal(root, anchor) should reject a wrong anchor"
+        );
+    }
 }
\ No newline at end of file
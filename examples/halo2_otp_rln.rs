@@ -0,0 +1,196 @@
+use clap::Parser;
+use halo2_base::{gates::GateChip, gates::GateInstructions, utils::ScalarField, AssignedValue, Context};
+use halo2_playground::scaffold::{cmd::Cli, run};
+use poseidon::PoseidonChip;
+use serde::{Deserialize, Serialize};
+
+// Poseidon constants
+const T: usize = 3;
+const RATE: usize = 2;
+const R_F: usize = 8;
+const R_P: usize = 57;
+
+// Valid for 100 years; TOTP interval 30 seconds
+// 100 * 365 * 24 * 60 * 2 = 105120000
+// log2 (105120000) = 27
+const LEVELS: usize = 27;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub secret: String,      // a_0, the user's identity secret witness
+    pub epoch: String,       // public epoch the proof is scoped to
+    pub signal_hash: String, // hash of the message/OTP being authorized this epoch
+    pub path_elements: [String; LEVELS],
+    pub path_index: [String; LEVELS],
+}
+
+fn otp_rln_proof<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) {
+    // `Context` can roughly be thought of as a single-threaded execution trace of a program we want to ZK prove. We do some post-processing on `Context` to optimally divide the execution trace into multiple columns in a PLONKish arithmetization
+    // More advanced usage with multi-threaded witness generation is possible, but we do not explain it here
+
+    let secret = F::from_str_vartime(&input.secret).expect("deserialize field element should not fail");
+    let epoch = F::from_str_vartime(&input.epoch).expect("deserialize field element should not fail");
+    let signal_hash = F::from_str_vartime(&input.signal_hash).expect("deserialize field element should not fail");
+    let path_elements = input.path_elements.map(|x: String| ctx.load_witness(F::from_str_vartime(&x).unwrap()));
+    let path_index = input.path_index.map(|x: String| ctx.load_witness(F::from_str_vartime(&x).unwrap()));
+
+    let a_0 = ctx.load_witness(secret);
+    let epoch = ctx.load_witness(epoch);
+    let signal_hash = ctx.load_witness(signal_hash);
+
+    // create a Gate chip that contains methods for basic arithmetic operations
+    let gate = GateChip::<F>::default();
+
+    // leaf = identity_commitment = Poseidon(a_0)
+    let mut leaf_poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+    leaf_poseidon.update(&[a_0]);
+    let leaf = leaf_poseidon.squeeze(ctx, &gate).unwrap();
+
+    // // Loop through the path elements
+    let mut level_hashes = vec![];
+    level_hashes.push(leaf.clone());
+
+    for i in 0..LEVELS {
+        // Should be 0 or 1
+        gate.assert_bit(ctx, path_index[i].clone());
+
+        // Instantiate inner poseidon instances
+        let mut inner_poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+
+        if *path_index[i].value() == F::zero() {
+            inner_poseidon.update(&[level_hashes[i], path_elements[i]]);
+        } else {
+            inner_poseidon.update(&[path_elements[i], level_hashes[i]]);
+        }
+        level_hashes.push(inner_poseidon.squeeze(ctx, &gate).unwrap());
+    }
+
+    let root = level_hashes[LEVELS];
+    // Public outputs are pushed in the order the caller/verifier contract expects them:
+    // root, epoch, share_x, share_y, nullifier.
+    make_public.push(root);
+    make_public.push(epoch);
+
+    // a_1 = Poseidon(a_0, epoch), the per-epoch share of the secret
+    let mut a_1_poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+    a_1_poseidon.update(&[a_0, epoch]);
+    let a_1 = a_1_poseidon.squeeze(ctx, &gate).unwrap();
+
+    // share_x = Poseidon(signal_hash)
+    let mut share_x_poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+    share_x_poseidon.update(&[signal_hash]);
+    let share_x = share_x_poseidon.squeeze(ctx, &gate).unwrap();
+    make_public.push(share_x);
+
+    // share_y = a_0 + a_1 * share_x, a point on the degree-1 Shamir line through (0, a_0)
+    let share_y = gate.mul_add(ctx, a_1, share_x, a_0);
+    make_public.push(share_y);
+
+    // nullifier = Poseidon(a_1), unique per identity per epoch so double-signaling is detectable
+    let mut nullifier_poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+    nullifier_poseidon.update(&[a_1]);
+    let nullifier = nullifier_poseidon.squeeze(ctx, &gate).unwrap();
+    make_public.push(nullifier);
+
+    println!(
+        "root: {:?}, epoch: {:?}, share_x: {:?}, share_y: {:?}, nullifier: {:?}",
+        root.value(),
+        epoch.value(),
+        share_x.value(),
+        share_y.value(),
+        nullifier.value()
+    );
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+    run(otp_rln_proof, args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CircuitInput;
+    use halo2_base::gates::builder::{GateThreadBuilder, GateCircuitBuilder};
+    use halo2_base::halo2_proofs::dev::MockProver;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_otp_rln_proof() {
+        let params = CircuitInput {
+            secret: "998877".to_string(),
+            epoch: "19".to_string(),
+            signal_hash: "12345".to_string(),
+            path_elements: ["1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999"].map(|x| x.to_string()),
+            path_index: ["1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1"].map(|x| x.to_string())
+        };
+
+        let k = 10u32;
+
+        // Instantiate Vec of AssignedValue<F> to store public inputs
+        let mut make_public = vec![];
+
+        let mut builder = GateThreadBuilder::<Fr>::mock();
+        otp_rln_proof(builder.main(0), params, &mut make_public);
+
+        builder.config(k as usize, Some(12));
+
+        let circuit = GateCircuitBuilder::mock(builder);
+        MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+
+        println!("Public inputs: {:?}", make_public);
+    }
+
+    /// This is the entire point of RLN: reusing the same identity in the same epoch for two
+    /// different signals leaks two points, `(share_x, share_y)`, on the same degree-1 Shamir line
+    /// `y = a_0 + a_1 * x` — which is enough to recover the secret `a_0`. The happy-path test
+    /// above only proves one signal is accepted; this proves the slashing property itself holds.
+    #[test]
+    fn test_otp_rln_reuse_leaks_secret() {
+        use halo2_base::halo2_proofs::halo2curves::ff::Field;
+
+        let secret = "998877".to_string();
+        let epoch = "19".to_string();
+        let path_elements: [String; LEVELS] = ["1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999","4837377","1234","11234","12222","118865","435676","494999"].map(|x| x.to_string());
+        let path_index: [String; LEVELS] = ["1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1","0","1","1","0","1","0","1"].map(|x| x.to_string());
+
+        let k = 10u32;
+
+        let run = |signal_hash: &str| -> (Fr, Fr) {
+            let params = CircuitInput {
+                secret: secret.clone(),
+                epoch: epoch.clone(),
+                signal_hash: signal_hash.to_string(),
+                path_elements: path_elements.clone(),
+                path_index: path_index.clone(),
+            };
+
+            let mut make_public = vec![];
+            let mut builder = GateThreadBuilder::<Fr>::mock();
+            otp_rln_proof(builder.main(0), params, &mut make_public);
+            builder.config(k as usize, Some(12));
+
+            let circuit = GateCircuitBuilder::mock(builder);
+            MockProver::run(k, &circuit, vec![]).unwrap().assert_satisfied();
+
+            // Public outputs are pushed as root, epoch, share_x, share_y, nullifier.
+            (*make_public[2].value(), *make_public[3].value())
+        };
+
+        let (x1, y1) = run("12345");
+        let (x2, y2) = run("54321");
+        assert_ne!(x1, x2, "two different signal_hashes must land on different share_x");
+
+        // Interpolate a_0 from the two points on the degree-1 line y = a_0 + a_1 * x.
+        let recovered_secret = (y1 * x2 - y2 * x1) * (x2 - x1).invert().unwrap();
+
+        let expected_secret = Fr::from_str_vartime(&secret).unwrap();
+        assert_eq!(recovered_secret, expected_secret, "reusing an identity across signals in the same epoch should leak a_0");
+    }
+}
@@ -0,0 +1,54 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum SnarkCmd {
+    /// Run the circuit through `MockProver` and check it is satisfied.
+    Mock,
+    /// Generate (or load) the proving/verifying keys for the circuit.
+    Keygen,
+    /// Generate a real SHPLONK proof over BN256 using the keys from `keygen`.
+    Prove,
+    /// Generate a Solidity verifier contract (and ABI-encoded calldata for a sample proof)
+    /// for the circuit's proving key.
+    GenEvmVerifier,
+    /// Recursively aggregate N independently generated `otp_merkle_proof` SNARKs into a single
+    /// proof, so a relayer can submit one on-chain verification for a whole batch of logins.
+    Aggregate {
+        /// Paths to the `Snark`s (as written by `prove`) to fold into one aggregated proof.
+        #[arg(long = "snarks", required = true, num_args = 1..)]
+        snarks: Vec<PathBuf>,
+    },
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: SnarkCmd,
+    /// Circuit degree, 2^k rows. If omitted, falls back to the degree recorded in the cached key
+    /// metadata from a previous `keygen`/`prove` run at the resolved `pk_path`; required if no
+    /// such cache exists yet.
+    #[arg(short, long = "degree")]
+    pub degree: Option<u32>,
+    /// JSON file with the circuit's `CircuitInput`. Required for every command except `aggregate`.
+    #[arg(short, long = "input")]
+    pub input_path: Option<PathBuf>,
+    /// Directory to write keys, proofs, and the generated Solidity verifier into.
+    #[arg(short, long = "output", default_value = "data")]
+    pub output_dir: PathBuf,
+    /// Name used to derive default file names under `output_dir` (e.g. `<name>.pk`, `<name>.vk`).
+    /// Defaults to `"circuit"`, or `"aggregation"` for the `aggregate` command, so running
+    /// `prove` and then `aggregate` against the same `output_dir` doesn't clobber the leaf
+    /// circuit's cached keys unless `--name` is explicitly reused.
+    #[arg(short, long)]
+    pub name: Option<String>,
+    /// Where to read/write the serialized proving key. Defaults to `<output_dir>/<name>.pk`.
+    #[arg(long = "pk-path")]
+    pub pk_path: Option<PathBuf>,
+    /// Where to read/write the serialized verifying key. Defaults to `<output_dir>/<name>.vk`.
+    #[arg(long = "vk-path")]
+    pub vk_path: Option<PathBuf>,
+    /// Ignore any cached proving/verifying keys on disk and regenerate them from scratch.
+    #[arg(long = "force-keygen", default_value_t = false)]
+    pub force_keygen: bool,
+}
@@ -0,0 +1,371 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use halo2_base::gates::builder::{CircuitBuilderStage, GateCircuitBuilder, GateThreadBuilder};
+use halo2_base::halo2_proofs::dev::MockProver;
+use halo2_base::halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_base::halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+use halo2_base::halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_base::halo2_proofs::SerdeFormat;
+use halo2_base::AssignedValue;
+use halo2_base::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use snark_verifier_sdk::evm::{encode_calldata, gen_evm_proof_shplonk, gen_evm_verifier_shplonk};
+use snark_verifier_sdk::halo2::aggregation::{AggregationCircuit, AggregationConfigParams, VerifierUniversality};
+use snark_verifier_sdk::halo2::gen_snark_shplonk;
+use snark_verifier_sdk::{gen_pk, Snark, SHPLONK};
+
+pub mod cmd;
+
+use cmd::{Cli, SnarkCmd};
+
+/// Reads `args.input_path`, runs `f` to build the circuit's execution trace, and then does
+/// whatever `args.command` asks for: a `MockProver` sanity check, keygen, a real SHPLONK proof,
+/// Solidity verifier + calldata generation, or folding a batch of SNARKs into one.
+pub fn run<T: DeserializeOwned + Clone>(
+    f: impl Fn(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone,
+    args: Cli,
+) {
+    fs::create_dir_all(&args.output_dir).expect("failed to create output directory");
+
+    if let SnarkCmd::Aggregate { snarks } = &args.command {
+        aggregate(&args, snarks);
+        return;
+    }
+
+    let input: T = serde_json::from_reader(BufReader::new(
+        File::open(args.input_path.as_ref().expect("`--input` is required for this command"))
+            .expect("input file should exist"),
+    ))
+    .expect("input file should deserialize into the circuit's `CircuitInput`");
+
+    let k = args.degree.or_else(|| cached_degree(&args)).expect(
+        "circuit degree `-k` is required (no cached key metadata was found to infer it from)",
+    );
+
+    let build = |stage: CircuitBuilderStage, input: T| {
+        let mut builder = GateThreadBuilder::<Fr>::from_stage(stage);
+        let mut make_public = vec![];
+        f.clone()(builder.main(0), input, &mut make_public);
+        let config = builder.config(k as usize, Some(20));
+        (builder, make_public, format!("{config:?}"))
+    };
+
+    match args.command {
+        SnarkCmd::Mock => {
+            let (builder, make_public, _shape) = build(CircuitBuilderStage::Mock, input);
+            let instances = make_public.iter().map(|a| *a.value()).collect::<Vec<_>>();
+            let circuit = GateCircuitBuilder::mock(builder);
+            MockProver::run(k, &circuit, vec![instances]).unwrap().assert_satisfied();
+            println!("mock proof verified");
+        }
+        SnarkCmd::Keygen => {
+            let params = srs(k, &args);
+            let (builder, _, shape) = build(CircuitBuilderStage::Keygen, input);
+            let circuit = GateCircuitBuilder::keygen(builder);
+            load_or_gen_pk(&params, &circuit, k, &shape, &args);
+            println!("proving key written to {:?}", pk_path(&args));
+        }
+        SnarkCmd::Prove => {
+            let params = srs(k, &args);
+            let (keygen_builder, _, shape) = build(CircuitBuilderStage::Keygen, input.clone());
+            let keygen_circuit = GateCircuitBuilder::keygen(keygen_builder);
+            let pk = load_or_gen_pk(&params, &keygen_circuit, k, &shape, &args);
+
+            let (builder, make_public, _shape) = build(CircuitBuilderStage::Prover, input);
+            let circuit = GateCircuitBuilder::prover(builder, keygen_circuit.break_points());
+            gen_snark_shplonk(&params, &pk, circuit, Some(&snark_path(&args)));
+            println!(
+                "real SHPLONK proof ({} public instance(s)) written to {:?}",
+                make_public.len(),
+                snark_path(&args)
+            );
+        }
+        SnarkCmd::GenEvmVerifier => {
+            let params = srs(k, &args);
+            let (keygen_builder, _, shape) = build(CircuitBuilderStage::Keygen, input.clone());
+            let keygen_circuit = GateCircuitBuilder::keygen(keygen_builder);
+            let pk = load_or_gen_pk(&params, &keygen_circuit, k, &shape, &args);
+
+            let (builder, make_public, _shape) = build(CircuitBuilderStage::Prover, input);
+            let circuit = GateCircuitBuilder::prover(builder, keygen_circuit.break_points());
+            let num_instance = vec![make_public.len()];
+
+            let deployment_code = gen_evm_verifier_shplonk::<GateCircuitBuilder<Fr>>(
+                &params,
+                pk.get_vk(),
+                num_instance,
+                Some(&sol_path(&args)),
+            );
+
+            let instances = vec![make_public.iter().map(|a| *a.value()).collect::<Vec<_>>()];
+            let proof = gen_evm_proof_shplonk(&params, &pk, circuit, instances.clone());
+            let calldata = encode_calldata(&instances, &proof);
+            fs::write(calldata_path(&args), hex::encode(&calldata)).expect("failed to write calldata");
+
+            println!(
+                "solidity verifier written to {:?} ({} bytes deployed bytecode), calldata written to {:?}",
+                sol_path(&args),
+                deployment_code.len(),
+                calldata_path(&args)
+            );
+        }
+        SnarkCmd::Aggregate { .. } => unreachable!("handled above before `input` is read"),
+    }
+}
+
+/// Folds `snark_paths` (each produced by `prove`, with its own `time`/`anchor` public instances
+/// against a shared anchor) into a single recursive SHPLONK proof. The aggregation circuit's
+/// public instances are simply the forwarded instances of every wrapped snark, so the aggregated
+/// proof's public output already commits to the full list of accepted `(time, anchor)` pairs.
+fn aggregate(args: &Cli, snark_paths: &[PathBuf]) {
+    let snarks: Vec<Snark> = snark_paths.iter().map(|p| read_snark(p)).collect();
+    let k = args.degree.or_else(|| cached_degree(args)).expect(
+        "circuit degree `-k` is required for aggregation (no cached key metadata was found to infer it from)",
+    );
+    let params = srs(k, args);
+
+    // `AggregationConfigParams::default()` leaves `num_advice`/`num_lookup_advice`/`num_fixed` at
+    // 0, which under-allocates columns for the verifier gadget. Build once at a rough starting
+    // config to measure what the wrapped snarks actually need, and let `calculate_params` derive
+    // the real column counts from that — the same two-pass approach `build()` above uses via
+    // `GateThreadBuilder::config`, just with snark-verifier-sdk's equivalent for the aggregation
+    // circuit.
+    let mut keygen_circuit = AggregationCircuit::new::<SHPLONK>(
+        CircuitBuilderStage::Keygen,
+        AggregationConfigParams { degree: k, lookup_bits: k as usize - 1, ..Default::default() },
+        &params,
+        snarks.clone(),
+        VerifierUniversality::None,
+    );
+    let config_params = keygen_circuit.calculate_params(Some(20));
+    let shape = format!("{config_params:?}");
+
+    let pk = load_or_gen_pk(&params, &keygen_circuit, k, &shape, args);
+    let break_points = keygen_circuit.break_points();
+
+    let prover_circuit = AggregationCircuit::new::<SHPLONK>(
+        CircuitBuilderStage::Prover,
+        config_params,
+        &params,
+        snarks,
+        VerifierUniversality::None,
+    )
+    .use_break_points(break_points);
+    let num_instances = prover_circuit.instances()[0].len();
+
+    gen_snark_shplonk(&params, &pk, prover_circuit, Some(&snark_path(args)));
+    println!(
+        "aggregated proof ({num_instances} public instance(s)) written to {:?}",
+        snark_path(args)
+    );
+}
+
+fn read_snark(path: &PathBuf) -> Snark {
+    bincode::deserialize_from(BufReader::new(File::open(path).expect("snark file should exist")))
+        .expect("snark file should deserialize into a `Snark`")
+}
+
+/// Highest degree any circuit in this binary (leaf or aggregation) is expected to need. We keep
+/// exactly one SRS file at this degree and trim it down per circuit rather than generating a
+/// fresh, unrelated SRS per `k` — see `srs()` below for why that distinction matters.
+const UNIVERSAL_SRS_DEGREE: u32 = 22;
+
+/// Loads (or generates) the single universal Powers-of-Tau SRS and trims it down to degree `k`.
+///
+/// Aggregation only works because the in-circuit pairing check over a wrapped leaf SNARK uses the
+/// *same* tau that produced that leaf's proof. Calling `ParamsKZG::setup` fresh for every distinct
+/// `k` — as a naive per-degree-keyed cache would — hands the leaf circuit and the aggregation
+/// circuit unrelated toxic waste, so an aggregated proof built that way would not actually attest
+/// to the wrapped SNARKs. Instead we keep one `kzg_bn254_{UNIVERSAL_SRS_DEGREE}.srs` file and use
+/// `ParamsKZG::downsize`, which truncates a universal SRS to a smaller degree without touching its
+/// tau, so every circuit this binary builds — at any `k` — shares the same trusted setup.
+///
+/// CAUTION: when no such file is already present, this runs a toy, locally-known setup via
+/// `OsRng` rather than loading a real trusted-setup/perpetual-powers-of-tau ceremony file. That's
+/// fine for `mock`/dev iteration, but a proof (and any `gen-evm-verifier` Solidity verifier) built
+/// on top of it is trivially forgeable by whoever ran this command — it is NOT safe to deploy
+/// on-chain or rely on for anything but local testing. Point this at a real ceremony's `.srs` file
+/// before using `prove`/`gen-evm-verifier`/`aggregate` output for anything real.
+fn srs(k: u32, args: &Cli) -> ParamsKZG<Bn256> {
+    assert!(
+        k <= UNIVERSAL_SRS_DEGREE,
+        "circuit degree {k} exceeds UNIVERSAL_SRS_DEGREE ({UNIVERSAL_SRS_DEGREE}); bump it"
+    );
+    let path = args.output_dir.join(format!("kzg_bn254_{UNIVERSAL_SRS_DEGREE}.srs"));
+    let mut params = if path.exists() {
+        ParamsKZG::read(&mut BufReader::new(File::open(&path).unwrap())).unwrap()
+    } else {
+        eprintln!(
+            "warning: no {path:?} found; generating a toy universal SRS locally. This is \
+             insecure and must not be used to back a `prove`/`gen-evm-verifier`/`aggregate` \
+             proof deployed on-chain."
+        );
+        let params = ParamsKZG::<Bn256>::setup(UNIVERSAL_SRS_DEGREE, rand_chacha::rand_core::OsRng);
+        let mut writer = BufWriter::new(File::create(&path).unwrap());
+        params.write(&mut writer).unwrap();
+        params
+    };
+    params.downsize(k);
+    params
+}
+
+/// Records the circuit shape a cached proving key was generated for, so a later run with a
+/// different `k`, or the same `k` but a different circuit shape (e.g. a different Merkle `depth`),
+/// doesn't silently load stale, incompatible keys.
+#[derive(Serialize, Deserialize)]
+struct KeyMeta {
+    degree: u32,
+    /// Debug-formatted gate/circuit config (e.g. `FlexGateConfigParams`/`AggregationConfigParams`)
+    /// the key was generated for. Opaque on purpose: we only need byte-for-byte equality, not to
+    /// parse it back.
+    shape: String,
+}
+
+fn key_meta_path(pk_path: &PathBuf) -> PathBuf {
+    pk_path.with_extension("meta.json")
+}
+
+fn read_key_meta(args: &Cli) -> Option<KeyMeta> {
+    let meta_path = key_meta_path(&pk_path(args));
+    serde_json::from_reader(BufReader::new(File::open(meta_path).ok()?)).ok()
+}
+
+/// The degree a previous `keygen`/`prove` run recorded for this `pk_path`, used as a fallback
+/// when `--degree` is omitted.
+fn cached_degree(args: &Cli) -> Option<u32> {
+    read_key_meta(args).map(|meta| meta.degree)
+}
+
+/// Loads the proving key from `args.pk_path` if its `KeyMeta` matches the requested circuit
+/// degree and shape and `--force-keygen` wasn't passed; otherwise runs keygen and persists the
+/// proving key, verifying key, and shape metadata for next time.
+fn load_or_gen_pk<C: snark_verifier_sdk::CircuitExt<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    circuit: &C,
+    k: u32,
+    shape: &str,
+    args: &Cli,
+) -> ProvingKey<G1Affine> {
+    let pk_path = pk_path(args);
+
+    let cached_meta_matches = !args.force_keygen
+        && read_key_meta(args).map(|meta| meta.degree == k && meta.shape == shape).unwrap_or(false);
+
+    if cached_meta_matches && pk_path.exists() {
+        if let Ok(file) = File::open(&pk_path) {
+            if let Ok(pk) = ProvingKey::read::<_, C>(&mut BufReader::new(file), SerdeFormat::RawBytes) {
+                println!("loaded cached proving key from {pk_path:?}");
+                return pk;
+            }
+        }
+    }
+
+    // `gen_pk` reads back an existing file at `pk_path` instead of running keygen, so drop a
+    // stale or force-discarded key first.
+    let _ = fs::remove_file(&pk_path);
+    let pk = gen_pk(params, circuit, Some(&pk_path));
+    serde_json::to_writer(
+        BufWriter::new(File::create(key_meta_path(&pk_path)).unwrap()),
+        &KeyMeta { degree: k, shape: shape.to_string() },
+    )
+    .expect("failed to write key metadata");
+    write_vk(pk.get_vk(), &vk_path(args));
+    pk
+}
+
+fn write_vk(vk: &VerifyingKey<G1Affine>, path: &PathBuf) {
+    let mut writer = BufWriter::new(File::create(path).expect("failed to create vk file"));
+    vk.write(&mut writer, SerdeFormat::RawBytes).expect("failed to write verifying key");
+}
+
+/// Default file-name stem for a command's outputs. Separate from `"circuit"` for `aggregate` so
+/// running `prove` and then `aggregate --snarks ...` against the same `--output` directory
+/// doesn't silently overwrite the leaf circuit's cached proving/verifying keys.
+fn name(args: &Cli) -> String {
+    args.name.clone().unwrap_or_else(|| {
+        if matches!(args.command, SnarkCmd::Aggregate { .. }) { "aggregation" } else { "circuit" }.to_string()
+    })
+}
+
+fn pk_path(args: &Cli) -> PathBuf {
+    args.pk_path.clone().unwrap_or_else(|| args.output_dir.join(format!("{}.pk", name(args))))
+}
+
+fn vk_path(args: &Cli) -> PathBuf {
+    args.vk_path.clone().unwrap_or_else(|| args.output_dir.join(format!("{}.vk", name(args))))
+}
+
+fn snark_path(args: &Cli) -> PathBuf {
+    args.output_dir.join(format!("{}.snark", name(args)))
+}
+
+fn sol_path(args: &Cli) -> PathBuf {
+    args.output_dir.join(format!("{}.Verifier.sol", name(args)))
+}
+
+fn calldata_path(args: &Cli) -> PathBuf {
+    args.output_dir.join(format!("{}.calldata", name(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_base::gates::GateChip;
+
+    /// Builds a trivial `out = a + b` snark against `params`, just so `test_aggregate_two_snarks`
+    /// below has something cheap to wrap. Mirrors the keygen/prove shape `run()` uses above.
+    fn trivial_add_snark(a: u64, b: u64, params: &ParamsKZG<Bn256>) -> Snark {
+        let k = params.k();
+        let build = |stage: CircuitBuilderStage| {
+            let mut builder = GateThreadBuilder::<Fr>::from_stage(stage);
+            let ctx = builder.main(0);
+            let gate = GateChip::<Fr>::default();
+            let a = ctx.load_witness(Fr::from(a));
+            let b = ctx.load_witness(Fr::from(b));
+            gate.add(ctx, a, b);
+            builder.config(k as usize, Some(9));
+            builder
+        };
+
+        let keygen_circuit = GateCircuitBuilder::keygen(build(CircuitBuilderStage::Keygen));
+        let pk = gen_pk(params, &keygen_circuit, None);
+
+        let circuit = GateCircuitBuilder::prover(build(CircuitBuilderStage::Prover), keygen_circuit.break_points());
+        gen_snark_shplonk(params, &pk, circuit, None::<&str>)
+    }
+
+    /// Exercises the aggregation circuit: two leaf snarks, proven against a universal SRS
+    /// downsized to the leaf degree, get folded into one `AggregationCircuit` built against the
+    /// same universal SRS at the (larger) aggregation degree, and `MockProver` checks the result.
+    /// The circuit is built here directly; `aggregate()` itself is not called.
+    #[test]
+    fn test_aggregate_two_snarks() {
+        let universal_k = 19u32;
+        let universal_params = ParamsKZG::<Bn256>::setup(universal_k, rand_chacha::rand_core::OsRng);
+
+        let leaf_k = 9u32;
+        let mut leaf_params = universal_params.clone();
+        leaf_params.downsize(leaf_k);
+
+        let snarks = vec![trivial_add_snark(2, 3, &leaf_params), trivial_add_snark(5, 7, &leaf_params)];
+
+        let agg_k = universal_k;
+        let rough_config =
+            AggregationConfigParams { degree: agg_k, lookup_bits: agg_k as usize - 1, ..Default::default() };
+        let mut agg_circuit = AggregationCircuit::new::<SHPLONK>(
+            CircuitBuilderStage::Mock,
+            rough_config,
+            &universal_params,
+            snarks,
+            VerifierUniversality::None,
+        );
+        agg_circuit.calculate_params(Some(20));
+
+        let instances = agg_circuit.instances();
+        MockProver::run(agg_k, &agg_circuit, instances).unwrap().assert_satisfied();
+    }
+}